@@ -0,0 +1,40 @@
+use std::{env, path::Path, process::Command};
+
+/// Records the git commit and rustc version for the build info metric.
+fn main() {
+    let commit = env::var("GIT_COMMIT")
+        .ok()
+        .or_else(|| output("git", &["rev-parse", "--short", "HEAD"]));
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = output(&rustc, &["--version"]);
+
+    println!(
+        "cargo:rustc-env=ULS_EXPORTER_GIT_COMMIT={}",
+        commit.as_deref().unwrap_or("unknown")
+    );
+    println!(
+        "cargo:rustc-env=ULS_EXPORTER_RUSTC_VERSION={}",
+        rustc_version.as_deref().unwrap_or("unknown")
+    );
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    if let Some(git_dir) = output("git", &["rev-parse", "--git-dir"]) {
+        // HEAD only changes on a checkout, a commit moves the branch it refers
+        // to, which is either a file of its own or in packed-refs.
+        println!("cargo:rerun-if-changed={}/HEAD", git_dir);
+        if let Some(head_ref) = output("git", &["symbolic-ref", "-q", "HEAD"]) {
+            println!("cargo:rerun-if-changed={}/{}", git_dir, head_ref);
+        }
+        let packed_refs = Path::new(&git_dir).join("packed-refs");
+        if packed_refs.exists() {
+            println!("cargo:rerun-if-changed={}", packed_refs.display());
+        }
+    }
+}
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
+}
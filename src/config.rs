@@ -0,0 +1,669 @@
+use std::{
+    fs,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    path::{Path, PathBuf},
+};
+
+use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+use crate::logging::LogFormat;
+
+/// Prometheus exporter for the Unity Floating License Server.
+///
+/// Every option can also be set through the environment variable listed with
+/// it. Command line and environment take precedence over the config file.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Args {
+    /// TOML file to read configuration from
+    #[arg(long, env = "ULS_EXPORTER_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Address this exporter binds to
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_BINDADDR",
+        default_value = "0.0.0.0:9837",
+        value_parser = parse_socket_addr
+    )]
+    pub bind_addr: SocketAddr,
+
+    /// Path the metrics are served on
+    #[arg(long, env = "ULS_EXPORTER_METRICS_PATH", default_value = "metrics")]
+    pub metrics_path: String,
+
+    /// Unix socket to serve on instead of the bind address
+    #[cfg(unix)]
+    #[arg(long, env = "ULS_EXPORTER_UNIX_SOCKET", conflicts_with = "tls_cert")]
+    pub unix_socket: Option<PathBuf>,
+
+    /// Base URL of the Unity License Server
+    #[arg(long, env = "ULS_BASE_URL")]
+    pub uls_base_url: Option<String>,
+
+    /// Comma separated base URLs of further Unity License Servers to scrape
+    #[arg(long, env = "ULS_BASE_URLS", value_delimiter = ',')]
+    pub uls_base_urls: Vec<String>,
+
+    /// Path of the status endpoint below the base URLs
+    #[arg(long, env = "ULS_STATUS_PATH", default_value = "/v1/admin/status")]
+    pub status_path: String,
+
+    /// Path of the lease endpoint below the base URLs
+    #[arg(long, env = "ULS_LEASE_PATH", default_value = "/v1/admin/lease")]
+    pub lease_path: String,
+
+    /// Timeout of a single request to the ULS in milliseconds
+    #[arg(long, env = "ULS_EXPORTER_TIMEOUT_MS", default_value_t = 5000)]
+    pub timeout_ms: u64,
+
+    /// Timeout for connecting to the ULS in milliseconds
+    #[arg(long, env = "ULS_EXPORTER_CONNECT_TIMEOUT_MS", default_value_t = 1000)]
+    pub connect_timeout_ms: u64,
+
+    /// Idle connections kept open per ULS, unlimited if unset
+    #[arg(long, env = "ULS_EXPORTER_POOL_MAX_IDLE")]
+    pub pool_max_idle: Option<usize>,
+
+    /// How long idle connections to the ULS are kept open in milliseconds,
+    /// 90000 if unset
+    #[arg(long, env = "ULS_EXPORTER_POOL_IDLE_TIMEOUT_MS")]
+    pub pool_idle_timeout_ms: Option<u64>,
+
+    /// How many redirects of the ULS are followed, 0 follows none
+    #[arg(long, env = "ULS_EXPORTER_MAX_REDIRECTS", default_value_t = 3)]
+    pub max_redirects: usize,
+
+    /// How often a failed request to the ULS is retried
+    #[arg(long, env = "ULS_EXPORTER_RETRIES", default_value_t = 2)]
+    pub retries: u32,
+
+    /// How many ULS are fetched from at the same time
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_CONCURRENCY",
+        default_value_t = 4,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub concurrency: u64,
+
+    /// Seconds to wait on startup for the ULS to become reachable before serving
+    #[arg(long, env = "ULS_EXPORTER_STARTUP_TIMEOUT")]
+    pub startup_timeout: Option<u64>,
+
+    /// Exit if a ULS can't be reached on startup instead of only warning
+    #[arg(long, env = "ULS_EXPORTER_FAIL_FAST")]
+    pub fail_fast: bool,
+
+    /// How long a scrape of the ULS is reused for further requests in
+    /// milliseconds, 0 disables caching
+    #[arg(long, env = "ULS_EXPORTER_CACHE_TTL_MS", default_value_t = 0)]
+    pub cache_ttl_ms: u64,
+
+    /// Number of seats, overriding the one reported by the ULS
+    #[arg(long, env = "ULS_TOTAL_SEATS")]
+    pub total_seats: Option<i64>,
+
+    /// Prefix of all metric names
+    #[arg(long, env = "ULS_EXPORTER_NAMESPACE", default_value = "uls")]
+    pub namespace: String,
+
+    /// Segment between the namespace and the name of all metrics
+    #[arg(long, env = "ULS_EXPORTER_SUBSYSTEM", default_value = "")]
+    pub subsystem: String,
+
+    /// Help text of uls_health
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_HEALTH_HELP",
+        default_value = "Health of the ULS"
+    )]
+    pub health_help: String,
+
+    /// Help text of uls_uptime_ms
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_UPTIME_HELP",
+        default_value = "Uptime of the ULS in ms"
+    )]
+    pub uptime_help: String,
+
+    /// Help text of uls_license_leased
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_LEASED_HELP",
+        default_value = "Currently leased ULS License"
+    )]
+    pub leased_help: String,
+
+    /// Comma separated `name=value` labels added to every metric
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_CONST_LABELS",
+        value_delimiter = ',',
+        value_parser = parse_label
+    )]
+    pub const_labels: Vec<(String, String)>,
+
+    /// How the user of a lease is exposed in its labels
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_ANONYMIZE_USERS",
+        value_enum,
+        default_value_t = AnonymizeUsers::Off
+    )]
+    pub anonymize_users: AnonymizeUsers,
+
+    /// Salt mixed into hashed user names
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_USER_SALT",
+        hide_env_values = true,
+        default_value = ""
+    )]
+    pub user_salt: String,
+
+    /// Leave revoked leases out of uls_license_leased instead of reporting them as 0
+    #[arg(long, env = "ULS_EXPORTER_HIDE_REVOKED")]
+    pub hide_revoked: bool,
+
+    /// Expose uls_license_revoked with a series per revoked lease
+    #[arg(long, env = "ULS_EXPORTER_REVOKED_SERIES")]
+    pub revoked_series: bool,
+
+    /// Which of the leases reported with the same lease id is kept, all of
+    /// them if off
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_DEDUP_LEASES",
+        value_enum,
+        default_value_t = DedupLeases::Off
+    )]
+    pub dedup_leases: DedupLeases,
+
+    /// Limit of the per lease series of a ULS, further leases are added up in
+    /// a series labeled overflow="true"
+    #[arg(long, env = "ULS_EXPORTER_MAX_SERIES")]
+    pub max_series: Option<usize>,
+
+    /// Number of per lease series of a ULS beyond which a warning is logged
+    /// and uls_high_cardinality is 1
+    #[arg(long, env = "ULS_EXPORTER_CARDINALITY_WARN")]
+    pub cardinality_warn: Option<usize>,
+
+    /// User-Agent header sent to the ULS
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_USER_AGENT",
+        default_value = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"))
+    )]
+    pub user_agent: String,
+
+    /// Proxy for requests to the ULS, instead of HTTP_PROXY and HTTPS_PROXY
+    #[arg(long, env = "ULS_EXPORTER_PROXY")]
+    pub proxy: Option<String>,
+
+    /// Interval in seconds to drop the connections to the ULS at, so that it is
+    /// resolved again and a changed address is picked up
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_DNS_REFRESH_SECS",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub dns_refresh_secs: Option<u64>,
+
+    /// Minimum TLS version of connections to the ULS (1.0, 1.1, 1.2). 1.3 is
+    /// not supported, as the native TLS backend can't be limited to it
+    #[arg(long, env = "ULS_EXPORTER_MIN_TLS", value_parser = parse_tls_version)]
+    pub min_tls: Option<reqwest::tls::Version>,
+
+    /// Whether requests to the ULS may use HTTP/2
+    #[arg(long, env = "ULS_EXPORTER_HTTP2", value_enum, default_value_t = Http2::Off)]
+    pub http2: Http2,
+
+    /// Comma separated `Name:Value` headers sent along with every request to
+    /// the ULS
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_EXTRA_HEADERS",
+        value_delimiter = ',',
+        value_parser = parse_header
+    )]
+    pub extra_headers: Vec<(String, String)>,
+
+    /// Lowercase the domains and hostnames of leases
+    #[arg(long, env = "ULS_EXPORTER_NORMALIZE_CASE")]
+    pub normalize_case: bool,
+
+    /// Lowercase the users of leases
+    #[arg(long, env = "ULS_EXPORTER_NORMALIZE_USER_CASE")]
+    pub normalize_user_case: bool,
+
+    /// Strip the domain from users reported as DOMAIN\user or user@domain
+    #[arg(long, env = "ULS_EXPORTER_STRIP_USER_DOMAIN")]
+    pub strip_user_domain: bool,
+
+    /// Which metrics are derived from the leases of the ULS
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_LEASE_MODE",
+        value_enum,
+        default_value_t = LeaseMode::Detailed
+    )]
+    pub lease_mode: LeaseMode,
+
+    /// Only expose the status of the ULS, same as --lease-mode off
+    #[arg(long, env = "ULS_EXPORTER_DISABLE_LEASES")]
+    pub disable_leases: bool,
+
+    /// Expect the lease list in pages of `{ items, nextPageToken }` and fetch
+    /// all of them
+    #[arg(long, env = "ULS_EXPORTER_PAGINATED")]
+    pub paginated: bool,
+
+    /// Bearer token for the ULS admin API
+    #[arg(
+        long,
+        env = "ULS_AUTH_TOKEN",
+        hide_env_values = true,
+        conflicts_with_all = ["basic_auth_user", "basic_auth_pass"]
+    )]
+    pub auth_token: Option<String>,
+
+    /// User for basic authentication against the ULS admin API
+    #[arg(long, env = "ULS_BASIC_AUTH_USER", requires = "basic_auth_pass")]
+    pub basic_auth_user: Option<String>,
+
+    /// Password for basic authentication against the ULS admin API
+    #[arg(
+        long,
+        env = "ULS_BASIC_AUTH_PASS",
+        hide_env_values = true,
+        requires = "basic_auth_user"
+    )]
+    pub basic_auth_pass: Option<String>,
+
+    /// Additional CA certificate (PEM) to trust for the ULS
+    #[arg(long, env = "ULS_CA_CERT_PATH")]
+    pub ca_cert_path: Option<PathBuf>,
+
+    /// Client certificate (PEM) presented to the ULS
+    #[arg(long, env = "ULS_CLIENT_CERT_PATH", requires = "client_key_path")]
+    pub client_cert_path: Option<PathBuf>,
+
+    /// Private key (PKCS#8 PEM) of the client certificate
+    #[arg(long, env = "ULS_CLIENT_KEY_PATH", requires = "client_cert_path")]
+    pub client_key_path: Option<PathBuf>,
+
+    /// Certificate to serve the exporter over HTTPS with
+    #[arg(long, env = "ULS_EXPORTER_TLS_CERT", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// Private key of the HTTPS certificate
+    #[arg(long, env = "ULS_EXPORTER_TLS_KEY", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Bearer token required to read the metrics
+    #[arg(long, env = "ULS_EXPORTER_METRICS_TOKEN", hide_env_values = true)]
+    pub metrics_token: Option<String>,
+
+    /// Comma separated networks allowed to read the metrics, everyone if empty
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_ALLOW_CIDRS",
+        value_delimiter = ',',
+        value_parser = parse_cidr
+    )]
+    pub allow_cidrs: Vec<IpNet>,
+
+    /// Also expose CPU, memory and file descriptor metrics of the exporter process
+    #[arg(long, env = "ULS_EXPORTER_PROCESS_METRICS")]
+    pub process_metrics: bool,
+
+    /// Also expose uls_exporter_info, labeled with the hostname of the exporter
+    #[arg(long, env = "ULS_EXPORTER_INFO")]
+    pub exporter_info: bool,
+
+    /// Hostname in uls_exporter_info instead of the one of the machine, e.g.
+    /// the pod name in a container
+    #[arg(long, env = "ULS_EXPORTER_HOSTNAME")]
+    pub hostname: Option<String>,
+
+    /// Metrics requests allowed per second, further ones are answered with 429
+    #[arg(long, env = "ULS_EXPORTER_RATE_LIMIT")]
+    pub rate_limit: Option<f64>,
+
+    /// Validate the configuration and exit without contacting the ULS
+    #[arg(long)]
+    pub check: bool,
+
+    /// Print the metrics once and exit instead of serving them
+    #[arg(long, env = "ULS_EXPORTER_ONESHOT", conflicts_with = "push_gateway")]
+    pub oneshot: bool,
+
+    /// Push the metrics to this Pushgateway once and exit instead of serving them
+    #[arg(long, env = "ULS_EXPORTER_PUSH_GATEWAY")]
+    pub push_gateway: Option<String>,
+
+    /// Job name to push the metrics as
+    #[arg(long, env = "ULS_EXPORTER_PUSH_JOB", default_value = "uls_exporter")]
+    pub push_job: String,
+
+    /// Comma separated `name=value` labels grouping the pushed metrics
+    #[arg(
+        long,
+        env = "ULS_EXPORTER_PUSH_GROUPING",
+        value_delimiter = ',',
+        value_parser = parse_label
+    )]
+    pub push_grouping: Vec<(String, String)>,
+
+    /// Comma separated upper bounds of the buckets of the duration histograms
+    /// in seconds
+    #[arg(long, env = "ULS_EXPORTER_DURATION_BUCKETS")]
+    pub duration_buckets: Option<String>,
+
+    /// Log level (off, error, warn, info, debug, trace)
+    #[arg(long, env = "ULS_EXPORTER_LOG_LEVEL", default_value = "info")]
+    pub log_level: String,
+
+    /// Format of the log output
+    #[arg(long, env = "ULS_EXPORTER_LOG_FORMAT", value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnonymizeUsers {
+    /// Expose user names as reported by the ULS
+    Off,
+    /// Replace user names with a salted hash
+    Hash,
+    /// Leave out the user label
+    Drop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DedupLeases {
+    /// Keep every lease, a repeated lease id overwrites the earlier series
+    Off,
+    /// Keep the first lease of a lease id
+    First,
+    /// Keep the last lease of a lease id
+    Last,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Http2 {
+    /// Only HTTP/1.1
+    Off,
+    /// HTTP/2 if the ULS offers it during the TLS handshake, only for https
+    Alpn,
+    /// HTTP/2 without negotiation, for a ULS known to speak it, also over http
+    PriorKnowledge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaseMode {
+    /// A series per lease along with the aggregates
+    Detailed,
+    /// Only counts of leases, per user and per domain
+    Aggregate,
+    /// Don't fetch the leases at all
+    Off,
+}
+
+impl Args {
+    /// Parses command line and environment, falling back to the values of the
+    /// config file if one is given, and validates the result.
+    pub fn load() -> Args {
+        // The config file has to be known before the real parse, as its values
+        // become the defaults of the command line.
+        let config_path = Args::command()
+            .ignore_errors(true)
+            .get_matches()
+            .get_one::<PathBuf>("config")
+            .cloned();
+
+        let mut command = Args::command();
+        if let Some(path) = config_path {
+            let config = Config::read(&path)
+                .unwrap_or_else(|e| fail(format!("failed to load {}: {}", path.display(), e)));
+            for (id, value) in config.defaults() {
+                command = command.mut_arg(id, |arg| arg.default_value(value).required(false));
+            }
+        }
+        let args = Args::from_arg_matches(&command.get_matches()).unwrap_or_else(|e| e.exit());
+        if let Err(e) = args.validate() {
+            fail(e);
+        }
+        args
+    }
+
+    /// Checks what clap can't, as values from the config file don't take part
+    /// in clap's conflict checks.
+    fn validate(&self) -> Result<(), String> {
+        if self.uls_base_url.is_none() && self.uls_base_urls.is_empty() {
+            return Err("a ULS base url is required".to_string());
+        }
+        for base_url in self.uls_base_url.iter().chain(&self.uls_base_urls) {
+            reqwest::Url::parse(base_url)
+                .map_err(|e| format!("invalid ULS base url {}: {}", base_url, e))?;
+        }
+        if self.auth_token.is_some()
+            && (self.basic_auth_user.is_some() || self.basic_auth_pass.is_some())
+        {
+            return Err("a ULS auth token can not be combined with basic auth".to_string());
+        }
+        if self
+            .rate_limit
+            .is_some_and(|rate| rate.is_nan() || rate <= 0.0)
+        {
+            return Err("the rate limit has to be positive".to_string());
+        }
+        // Prometheus refuses metrics without a help text.
+        if [&self.health_help, &self.uptime_help, &self.leased_help]
+            .iter()
+            .any(|help| help.trim().is_empty())
+        {
+            return Err("help texts can not be empty".to_string());
+        }
+        if self.basic_auth_user.is_some() != self.basic_auth_pass.is_some() {
+            return Err("basic auth needs both a user and a password".to_string());
+        }
+        // Otherwise building the first metric fails.
+        if !is_metric_name(&self.namespace) {
+            return Err(format!("invalid metric namespace \"{}\"", self.namespace));
+        }
+        if !self.subsystem.is_empty() && !is_metric_name(&self.subsystem) {
+            return Err(format!("invalid metric subsystem \"{}\"", self.subsystem));
+        }
+        Ok(())
+    }
+}
+
+/// Whether `s` is a valid metric name, or part of one.
+fn is_metric_name(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Contents of the `--config` file. Keys mirror the command line options.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    bind_addr: Option<String>,
+    uls_base_url: Option<String>,
+    uls_base_urls: Option<Vec<String>>,
+    timeout_ms: Option<u64>,
+    connect_timeout_ms: Option<u64>,
+    retries: Option<u32>,
+    namespace: Option<String>,
+    subsystem: Option<String>,
+    auth: Option<AuthConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AuthConfig {
+    token: Option<String>,
+    basic_user: Option<String>,
+    basic_pass: Option<String>,
+}
+
+impl Config {
+    fn read(path: &Path) -> anyhow::Result<Config> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// The configured values by the id of the argument they are defaults for.
+    fn defaults(self) -> Vec<(&'static str, String)> {
+        let auth = self.auth.unwrap_or_default();
+        vec![
+            ("bind_addr", self.bind_addr),
+            ("uls_base_url", self.uls_base_url),
+            (
+                "uls_base_urls",
+                self.uls_base_urls.map(|urls| urls.join(",")),
+            ),
+            ("timeout_ms", self.timeout_ms.map(|v| v.to_string())),
+            (
+                "connect_timeout_ms",
+                self.connect_timeout_ms.map(|v| v.to_string()),
+            ),
+            ("retries", self.retries.map(|v| v.to_string())),
+            ("namespace", self.namespace),
+            ("subsystem", self.subsystem),
+            ("auth_token", auth.token),
+            ("basic_auth_user", auth.basic_user),
+            ("basic_auth_pass", auth.basic_pass),
+        ]
+        .into_iter()
+        .filter_map(|(id, value)| Some((id, value?)))
+        .collect()
+    }
+}
+
+fn parse_socket_addr(s: &str) -> Result<SocketAddr, String> {
+    s.to_socket_addrs()
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or_else(|| format!("{} does not resolve to an address", s))
+}
+
+/// Parses a network, or a single address as a network of its own.
+fn parse_cidr(s: &str) -> Result<IpNet, String> {
+    let s = s.trim();
+    s.parse::<IpNet>()
+        .or_else(|_| s.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| format!("invalid network {}", s))
+}
+
+fn parse_label(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("label {} is not of the form name=value", s))?;
+    let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_name {
+        return Err(format!("invalid label name \"{}\"", name));
+    }
+    // Prometheus rejects a whole scrape in which a series repeats a label.
+    if RESERVED_LABELS.contains(&name) {
+        return Err(format!("the {} label is set by the exporter", name));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Names of the labels the exporter sets on its metrics itself
+const RESERVED_LABELS: &[&str] = &[
+    "server",
+    "lease_id",
+    "lease_user",
+    "lease_hostname",
+    "lease_domain",
+    "product",
+    "overflow",
+    "user",
+    "domain",
+    "endpoint",
+    "reason",
+    "status",
+    "version",
+    "build",
+    "revision",
+    "rustc_version",
+    "hostname",
+    "error",
+    "le",
+    "quantile",
+];
+
+fn parse_tls_version(s: &str) -> Result<reqwest::tls::Version, String> {
+    use reqwest::tls::Version;
+
+    match s.trim() {
+        "1.0" => Ok(Version::TLS_1_0),
+        "1.1" => Ok(Version::TLS_1_1),
+        "1.2" => Ok(Version::TLS_1_2),
+        // native-tls can't be limited to TLS 1.3.
+        "1.3" => Err("requiring TLS 1.3 is not supported by the TLS backend".to_string()),
+        _ => Err(format!(
+            "unknown TLS version {}, expected 1.0, 1.1 or 1.2",
+            s
+        )),
+    }
+}
+
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    use reqwest::header::{HeaderName, HeaderValue};
+
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("header {} is not of the form Name:Value", s))?;
+    let (name, value) = (name.trim(), value.trim());
+    HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| format!("invalid header name \"{}\"", name))?;
+    HeaderValue::from_str(value).map_err(|_| format!("invalid value of header {}", name))?;
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Reports an invalid configuration the same way clap reports bad arguments
+/// and exits.
+pub fn fail(message: impl std::fmt::Display) -> ! {
+    Args::command()
+        .error(clap::error::ErrorKind::ValueValidation, message)
+        .exit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metric_names() {
+        assert!(is_metric_name("uls"));
+        assert!(is_metric_name("_my:ns2"));
+        assert!(!is_metric_name(""));
+        assert!(!is_metric_name("my-ns"));
+        assert!(!is_metric_name("2uls"));
+    }
+
+    #[test]
+    fn labels_of_the_exporter_are_reserved() {
+        assert_eq!(
+            parse_label("site=berlin"),
+            Ok(("site".to_string(), "berlin".to_string()))
+        );
+        for name in RESERVED_LABELS {
+            let error = parse_label(&format!("{}=x", name)).unwrap_err();
+            assert!(error.contains("set by the exporter"), "{}", error);
+        }
+        assert!(parse_label("1site=berlin").is_err());
+        assert!(parse_label("site").is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use std::io::Write;
+
+use prometheus::{
+    proto::{LabelPair, MetricFamily, MetricType},
+    Encoder,
+};
+
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Encodes metric families in the OpenMetrics text format, which the
+/// prometheus crate does not provide an encoder for.
+pub struct OpenMetricsEncoder;
+
+impl Encoder for OpenMetricsEncoder {
+    fn encode<W: Write>(
+        &self,
+        families: &[MetricFamily],
+        writer: &mut W,
+    ) -> prometheus::Result<()> {
+        for family in families {
+            let name = family.get_name();
+            let (kind, family_name) = match family.get_field_type() {
+                // OpenMetrics names counters without the suffix of their samples.
+                MetricType::COUNTER => ("counter", name.strip_suffix("_total").unwrap_or(name)),
+                MetricType::GAUGE => ("gauge", name),
+                MetricType::HISTOGRAM => ("histogram", name),
+                MetricType::SUMMARY => ("summary", name),
+                MetricType::UNTYPED => ("unknown", name),
+            };
+            writeln!(writer, "# TYPE {} {}", family_name, kind)?;
+            writeln!(
+                writer,
+                "# HELP {} {}",
+                family_name,
+                escape(family.get_help())
+            )?;
+
+            for metric in family.get_metric() {
+                let labels = metric.get_label();
+                match family.get_field_type() {
+                    MetricType::COUNTER => {
+                        let name = format!("{}_total", family_name);
+                        let value = metric.get_counter().get_value();
+                        write_sample(writer, &name, labels, None, value)?;
+                    }
+                    MetricType::GAUGE => {
+                        write_sample(writer, name, labels, None, metric.get_gauge().get_value())?;
+                    }
+                    MetricType::UNTYPED => {
+                        let value = metric.get_untyped().get_value();
+                        write_sample(writer, name, labels, None, value)?;
+                    }
+                    MetricType::HISTOGRAM => {
+                        let histogram = metric.get_histogram();
+                        let bucket = format!("{}_bucket", name);
+                        for b in histogram.get_bucket() {
+                            let le = ("le", format_value(b.get_upper_bound()));
+                            let count = b.get_cumulative_count() as f64;
+                            write_sample(writer, &bucket, labels, Some(le), count)?;
+                        }
+                        let count = histogram.get_sample_count() as f64;
+                        let inf = ("le", "+Inf".to_string());
+                        write_sample(writer, &bucket, labels, Some(inf), count)?;
+                        let sum = format!("{}_sum", name);
+                        write_sample(writer, &sum, labels, None, histogram.get_sample_sum())?;
+                        write_sample(writer, &format!("{}_count", name), labels, None, count)?;
+                    }
+                    MetricType::SUMMARY => {
+                        let summary = metric.get_summary();
+                        for q in summary.get_quantile() {
+                            let quantile = ("quantile", format_value(q.get_quantile()));
+                            write_sample(writer, name, labels, Some(quantile), q.get_value())?;
+                        }
+                        let sum = format!("{}_sum", name);
+                        write_sample(writer, &sum, labels, None, summary.get_sample_sum())?;
+                        let count = summary.get_sample_count() as f64;
+                        write_sample(writer, &format!("{}_count", name), labels, None, count)?;
+                    }
+                }
+            }
+        }
+        writeln!(writer, "# EOF")?;
+        Ok(())
+    }
+
+    fn format_type(&self) -> &str {
+        CONTENT_TYPE
+    }
+}
+
+/// Whether an `Accept` header asks for OpenMetrics.
+pub fn accepted(accept: Option<&str>) -> bool {
+    accept.unwrap_or_default().split(',').any(|media_type| {
+        media_type
+            .trim()
+            .starts_with("application/openmetrics-text")
+    })
+}
+
+fn write_sample<W: Write>(
+    writer: &mut W,
+    name: &str,
+    labels: &[LabelPair],
+    extra: Option<(&str, String)>,
+    value: f64,
+) -> prometheus::Result<()> {
+    let mut pairs = labels
+        .iter()
+        .map(|l| format!("{}=\"{}\"", l.get_name(), escape(l.get_value())))
+        .collect::<Vec<_>>();
+    if let Some((label, value)) = extra {
+        pairs.push(format!("{}=\"{}\"", label, value));
+    }
+    if pairs.is_empty() {
+        writeln!(writer, "{} {}", name, format_value(value))?;
+    } else {
+        writeln!(
+            writer,
+            "{}{{{}}} {}",
+            name,
+            pairs.join(","),
+            format_value(value)
+        )?;
+    }
+    Ok(())
+}
+
+fn format_value(v: f64) -> String {
+    if v.is_nan() {
+        "NaN".to_string()
+    } else if v == f64::INFINITY {
+        "+Inf".to_string()
+    } else if v == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        v.to_string()
+    }
+}
+
+/// Escapes a label value or help text, which OpenMetrics escapes alike.
+fn escape(v: &str) -> String {
+    let mut escaped = String::with_capacity(v.len());
+    for c in v.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '"' => escaped.push_str("\\\""),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::{Histogram, HistogramOpts, IntCounter, IntGaugeVec, Opts, Registry};
+
+    use super::*;
+
+    fn encode(r: &Registry) -> String {
+        let mut buffer = vec![];
+        OpenMetricsEncoder.encode(&r.gather(), &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn counters_are_named_without_total() {
+        let r = Registry::new();
+        let counter = IntCounter::new("requests_total", "Requests").unwrap();
+        counter.inc_by(3);
+        r.register(Box::new(counter)).unwrap();
+        assert_eq!(
+            encode(&r),
+            "# TYPE requests counter\n# HELP requests Requests\nrequests_total 3\n# EOF\n"
+        );
+    }
+
+    #[test]
+    fn histograms_end_with_inf_sum_and_count() {
+        let r = Registry::new();
+        let histogram =
+            Histogram::with_opts(HistogramOpts::new("duration", "Duration").buckets(vec![1.0]))
+                .unwrap();
+        histogram.observe(0.5);
+        histogram.observe(2.0);
+        r.register(Box::new(histogram)).unwrap();
+        let output = encode(&r);
+        let samples = output
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            samples,
+            vec![
+                r#"duration_bucket{le="1"} 1"#,
+                r#"duration_bucket{le="+Inf"} 2"#,
+                "duration_sum 2.5",
+                "duration_count 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn label_values_and_help_are_escaped() {
+        let r = Registry::new();
+        let gauge = IntGaugeVec::new(
+            Opts::new("leased", "Leased \"seats\"\nof a \\ULS"),
+            &["user"],
+        )
+        .unwrap();
+        gauge.with_label_values(&["CORP\\alice \"a\"\n"]).set(1);
+        r.register(Box::new(gauge)).unwrap();
+        let output = encode(&r);
+        assert!(output
+            .lines()
+            .any(|line| line == r#"# HELP leased Leased \"seats\"\nof a \\ULS"#));
+        assert!(output
+            .lines()
+            .any(|line| line == r#"leased{user="CORP\\alice \"a\"\n"} 1"#));
+    }
+
+    #[test]
+    fn output_ends_with_eof() {
+        assert_eq!(encode(&Registry::new()), "# EOF\n");
+        let r = Registry::new();
+        r.register(Box::new(IntCounter::new("a_total", "A").unwrap()))
+            .unwrap();
+        assert!(encode(&r).ends_with("\na_total 0\n# EOF\n"));
+    }
+}
@@ -0,0 +1,251 @@
+//! Runs the exporter once against a mock ULS and checks the metrics it prints.
+
+use std::{net::SocketAddr, process::Command};
+
+use warp::Filter;
+
+const HEALTHY: &str = r#"{"serverStatus":"Healthy","serverUpTimeMs":123456}"#;
+const UNHEALTHY: &str = r#"{"serverStatus":"Unhealthy","serverUpTimeMs":42}"#;
+const LEASES: &str = r#"[
+    {
+        "floatingLeaseId": 1,
+        "isRevoked": false,
+        "clientEntitlementContext": {
+            "EnvironmentDomain": "CORP",
+            "EnvironmentHostname": "PC1",
+            "EnvironmentUser": "alice"
+        }
+    },
+    {
+        "floatingLeaseId": 2,
+        "isRevoked": true,
+        "clientEntitlementContext": {
+            "EnvironmentDomain": "CORP",
+            "EnvironmentHostname": "PC2",
+            "EnvironmentUser": "bob"
+        }
+    }
+]"#;
+
+/// Serves `status` and `leases` on the admin API paths of the ULS.
+fn mock_uls(status: &'static str, leases: &'static str) -> SocketAddr {
+    let json =
+        |body: &'static str| warp::reply::with_header(body, "content-type", "application/json");
+    let status = warp::path!("v1" / "admin" / "status").map(move || json(status));
+    let leases = warp::path!("v1" / "admin" / "lease").map(move || json(leases));
+    let (addr, server) = warp::serve(status.or(leases)).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    addr
+}
+
+/// Runs the exporter with `--oneshot` against `addr`, returning whether it
+/// succeeded and what it printed.
+async fn oneshot(addr: SocketAddr) -> (bool, String) {
+    oneshot_with(addr, &[]).await
+}
+
+/// Same as [`oneshot`], with additional environment variables.
+async fn oneshot_with(addr: SocketAddr, envs: &'static [(&str, &str)]) -> (bool, String) {
+    tokio::task::spawn_blocking(move || {
+        // Cleared so that neither proxies nor a local configuration interfere.
+        let output = Command::new(env!("CARGO_BIN_EXE_unity-fls-exporter"))
+            .env_clear()
+            .env("ULS_BASE_URL", format!("http://{}", addr))
+            .env("ULS_EXPORTER_LOG_LEVEL", "off")
+            .envs(envs.iter().copied())
+            .arg("--oneshot")
+            .output()
+            .expect("failed to run the exporter");
+        (
+            output.status.success(),
+            String::from_utf8(output.stdout).unwrap(),
+        )
+    })
+    .await
+    .unwrap()
+}
+
+/// The sample of `name` with exactly `labels`, besides the server label.
+fn sample<'a>(output: &'a str, name: &str, labels: &str) -> Option<&'a str> {
+    output.lines().find_map(|line| {
+        let rest = line.strip_prefix(name)?.strip_prefix('{')?;
+        let (line_labels, value) = rest.split_once("} ")?;
+        let line_labels = line_labels
+            .split(',')
+            .filter(|label| !label.starts_with("server="))
+            .collect::<Vec<_>>()
+            .join(",");
+        (line_labels == labels).then_some(value)
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn healthy() {
+    let (success, output) = oneshot(mock_uls(HEALTHY, LEASES)).await;
+
+    assert!(success);
+    assert_eq!(sample(&output, "uls_health", ""), Some("1"));
+    assert_eq!(sample(&output, "uls_uptime_ms", ""), Some("123456"));
+    assert_eq!(
+        sample(&output, "uls_endpoint_up", r#"endpoint="status""#),
+        Some("1")
+    );
+    assert_eq!(
+        sample(&output, "uls_endpoint_up", r#"endpoint="lease""#),
+        Some("1")
+    );
+    assert_eq!(
+        sample(
+            &output,
+            "uls_license_leased",
+            r#"lease_domain="CORP",lease_hostname="PC1",lease_id="1",lease_user="alice",product="unknown""#
+        ),
+        Some("1")
+    );
+    assert_eq!(
+        sample(
+            &output,
+            "uls_license_leased",
+            r#"lease_domain="CORP",lease_hostname="PC2",lease_id="2",lease_user="bob",product="unknown""#
+        ),
+        Some("0")
+    );
+    assert_eq!(
+        sample(&output, "uls_licenses_leased_count", r#"product="unknown""#),
+        Some("1")
+    );
+    // Covers every series of the output, including its own.
+    let series = output.lines().filter(|line| !line.starts_with('#')).count();
+    assert_eq!(
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix("uls_scrape_collector_series ")),
+        Some(series.to_string().as_str())
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unhealthy() {
+    let (success, output) = oneshot(mock_uls(UNHEALTHY, LEASES)).await;
+
+    assert!(success);
+    assert_eq!(sample(&output, "uls_health", ""), Some("0"));
+    assert_eq!(sample(&output, "uls_uptime_ms", ""), Some("42"));
+    // The leases of an unhealthy ULS are not trusted.
+    assert!(!output.contains("uls_license_leased{"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn empty_leases() {
+    let (success, output) = oneshot(mock_uls(HEALTHY, "[]")).await;
+
+    assert!(success);
+    assert_eq!(sample(&output, "uls_health", ""), Some("1"));
+    assert!(!output.contains("uls_license_leased{"));
+    assert_eq!(
+        sample(&output, "uls_licenses_leased_count", r#"product="unknown""#),
+        Some("0")
+    );
+    assert_eq!(sample(&output, "uls_active_users", ""), Some("0"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn malformed_leases() {
+    let (success, output) = oneshot(mock_uls(HEALTHY, "{")).await;
+
+    // The status metrics stay, only the lease endpoint is down.
+    assert!(success);
+    assert_eq!(sample(&output, "uls_health", ""), Some("1"));
+    assert_eq!(
+        sample(&output, "uls_endpoint_up", r#"endpoint="status""#),
+        Some("1")
+    );
+    assert_eq!(
+        sample(&output, "uls_endpoint_up", r#"endpoint="lease""#),
+        Some("0")
+    );
+    assert_eq!(sample(&output, "uls_lease_scrape_success", ""), Some("0"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cycling_pages() {
+    // Hands out the page tokens a, b, a, ... without ever reaching the end.
+    let json =
+        |body: &'static str| warp::reply::with_header(body, "content-type", "application/json");
+    let status = warp::path!("v1" / "admin" / "status").map(move || json(HEALTHY));
+    let leases = warp::path!("v1" / "admin" / "lease")
+        .and(warp::query::<std::collections::HashMap<String, String>>())
+        .map(move |query: std::collections::HashMap<String, String>| {
+            match query.get("pageToken").map(String::as_str) {
+                Some("a") => json(r#"{"items":[],"nextPageToken":"b"}"#),
+                _ => json(r#"{"items":[],"nextPageToken":"a"}"#),
+            }
+        });
+    let (addr, server) = warp::serve(status.or(leases)).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    let (success, output) = oneshot_with(addr, &[("ULS_EXPORTER_PAGINATED", "true")]).await;
+
+    assert!(success);
+    assert_eq!(sample(&output, "uls_health", ""), Some("1"));
+    assert_eq!(
+        sample(&output, "uls_endpoint_up", r#"endpoint="lease""#),
+        Some("0")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn http2_prior_knowledge() {
+    // Without negotiation, the requests only succeed if both sides speak h2.
+    let (success, output) = oneshot_with(
+        mock_uls(HEALTHY, LEASES),
+        &[("ULS_EXPORTER_HTTP2", "prior-knowledge")],
+    )
+    .await;
+
+    assert!(success);
+    assert_eq!(sample(&output, "uls_health", ""), Some("1"));
+    assert_eq!(
+        sample(&output, "uls_licenses_leased_count", r#"product="unknown""#),
+        Some("1")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn exporter_info() {
+    let (success, output) = oneshot_with(
+        mock_uls(HEALTHY, LEASES),
+        &[
+            ("ULS_EXPORTER_INFO", "true"),
+            ("ULS_EXPORTER_HOSTNAME", "replica-1"),
+        ],
+    )
+    .await;
+
+    assert!(success);
+    assert_eq!(
+        sample(&output, "uls_exporter_info", r#"hostname="replica-1""#),
+        Some("1")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unreachable() {
+    // Bound and dropped right away, so nothing listens on it.
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let (success, output) = oneshot(addr).await;
+
+    assert!(!success);
+    assert_eq!(sample(&output, "uls_scrape_success", ""), Some("0"));
+    assert_eq!(
+        sample(&output, "uls_endpoint_up", r#"endpoint="status""#),
+        Some("0")
+    );
+    assert_eq!(
+        sample(&output, "uls_endpoint_up", r#"endpoint="lease""#),
+        Some("0")
+    );
+}